@@ -1,15 +1,22 @@
 #![deny(clippy::all)]
 
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
 use napi_derive::napi;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU32, AtomicU64, Ordering};
+use std::sync::{mpsc, Mutex};
 use std::thread;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use windows::{
     // core::*,
     Win32::Foundation::*,
     Win32::System::Com::*,
+    Win32::System::DataExchange::*,
     Win32::System::LibraryLoader::GetModuleHandleW,
+    Win32::System::Memory::*,
+    Win32::System::Ole::CF_UNICODETEXT,
+    Win32::System::Threading::GetCurrentThreadId,
     Win32::UI::Accessibility::*,
+    Win32::UI::Input::KeyboardAndMouse::*,
     Win32::UI::WindowsAndMessaging::*,
 };
 
@@ -17,32 +24,104 @@ use windows::{
 // 0 表示未按下
 static MOUSE_DOWN_TIME: AtomicU64 = AtomicU64::new(0);
 
-// 定义“长按/拖拽”的阈值 (毫秒)
-// 如果按下到抬起的时间小于这个值，被视为普通点击，不触发识别
-const SELECTION_THRESHOLD_MS: u64 = 200;
+// “长按/拖拽”的阈值 (毫秒)，如果按下到抬起的时间小于这个值，被视为普通点击，不触发识别
+// 通过 selection_configure 在运行时调整，默认 200ms
+static SELECTION_THRESHOLD_MS: AtomicU64 = AtomicU64::new(200);
+
+// 松开鼠标/键盘之后，到真正去读 UIA 选区之间的延迟 (毫秒)，留给目标程序完成渲染和内部状态更新
+// 同样通过 selection_configure 调整，默认 50ms
+static POST_RELEASE_DELAY_MS: AtomicU64 = AtomicU64::new(50);
+
+// 模拟 Ctrl+C 之后，等待目标程序把文本写入剪贴板所需的时间 (毫秒)
+const CLIPBOARD_COPY_DELAY_MS: u64 = 100;
+
+// 监听线程的线程 id，0 表示当前没有在运行；selection_stop 用它把 WM_QUIT 投递过去
+static MONITOR_THREAD_ID: AtomicU32 = AtomicU32::new(0);
+
+// 防止合成的 Ctrl+C 按键被我们自己的钩子当成新的选区变化再次触发
+static SYNTHETIC_COPY_IN_PROGRESS: AtomicBool = AtomicBool::new(false);
+
+// Shift 是否正被按住，键盘钩子用它判断 Shift+方向键/Home/End 这类选区扩展操作
+static SHIFT_DOWN: AtomicBool = AtomicBool::new(false);
+
+// 上一次由键盘触发识别的时间戳（毫秒），用于去抖
+static LAST_KEYBOARD_TRIGGER_TIME: AtomicU64 = AtomicU64::new(0);
+
+// 键盘触发的去抖窗口 (毫秒)：连发的方向键/Ctrl+A 在这个时间内只触发一次识别
+const KEYBOARD_DEBOUNCE_MS: u64 = 150;
+
+// 上一次 WM_LBUTTONDOWN 的消息时间戳/坐标，用来识别双击 (双击选中一个单词)
+static LAST_CLICK_TIME: AtomicU32 = AtomicU32::new(0);
+static LAST_CLICK_X: AtomicI32 = AtomicI32::new(0);
+static LAST_CLICK_Y: AtomicI32 = AtomicI32::new(0);
+
+// 本次按下是否被识别为双击的第二次点击；在随之而来的 WM_LBUTTONUP 里消费掉
+static PENDING_DOUBLE_CLICK: AtomicBool = AtomicBool::new(false);
+
+// JS 侧注册的回调，捕获到文本后通过它送回 Node 事件循环
+// 用 Mutex 而不是 OnceLock 存放，这样 selection_stop 之后可以再次调用 selection_initialize 重新注册
+static SELECTION_CALLBACK: Mutex<Option<ThreadsafeFunction<SelectionEvent>>> = Mutex::new(None);
+
+// 一次选区捕获事件，经 ThreadsafeFunction 跨线程送回 JS
+#[napi(object)]
+pub struct SelectionEvent {
+    pub text: String,
+    pub duration_ms: u32,
+    pub trigger_kind: String,
+    // 选区在屏幕上的外接矩形，UIA 没能给出坐标时为 None/null（比如走了剪贴板兜底方案）
+    pub rect_x: Option<f64>,
+    pub rect_y: Option<f64>,
+    pub rect_width: Option<f64>,
+    pub rect_height: Option<f64>,
+    // 选区所在应用的信息，方便上层定位悬浮工具栏的位置、按来源应用过滤
+    pub process_id: u32,
+    pub class_name: String,
+    pub control_type: String,
+}
 
 // -----------------------------------------------------------------------------
 // 鼠标钩子回调函数 (必须是 extern "system")
 // -----------------------------------------------------------------------------
 unsafe extern "system" fn mouse_hook_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
-    // 如果 code < 0，必须直接透传给下一个钩子
-    if code >= 0 {
+    // 如果 code < 0，必须直接透传给下一个钩子；正在合成 Ctrl+C 时也直接透传，避免误判
+    if code >= 0 && !SYNTHETIC_COPY_IN_PROGRESS.load(Ordering::SeqCst) {
         let msg = wparam.0 as u32;
 
         match msg {
             WM_LBUTTONDOWN => {
-                // 记录按下时间
+                // 记录按下时间，用于之后判断是拖拽还是普通点击
                 let now = SystemTime::now()
                     .duration_since(UNIX_EPOCH)
                     .unwrap_or_default()
                     .as_millis() as u64;
                 MOUSE_DOWN_TIME.store(now, Ordering::SeqCst);
+
+                // 用消息自带的时间戳/坐标和上一次按下比较，判断这次是不是双击的第二下，
+                // 这样用的是和系统一致的语义 (对应 MPC-HC 等程序 GetMessageTime/GetDoubleClickTime 的做法)
+                let ms = unsafe { &*(lparam.0 as *const MSLLHOOKSTRUCT) };
+                let last_time = LAST_CLICK_TIME.swap(ms.time, Ordering::SeqCst);
+                let last_x = LAST_CLICK_X.swap(ms.pt.x, Ordering::SeqCst);
+                let last_y = LAST_CLICK_Y.swap(ms.pt.y, Ordering::SeqCst);
+
+                let double_click_time = unsafe { GetDoubleClickTime() };
+                let tol_x = unsafe { GetSystemMetrics(SM_CXDOUBLECLK) };
+                let tol_y = unsafe { GetSystemMetrics(SM_CYDOUBLECLK) };
+
+                let within_time = last_time != 0 && ms.time.wrapping_sub(last_time) <= double_click_time;
+                let within_distance =
+                    (ms.pt.x - last_x).abs() <= tol_x && (ms.pt.y - last_y).abs() <= tol_y;
+
+                PENDING_DOUBLE_CLICK.store(within_time && within_distance, Ordering::SeqCst);
             }
             WM_LBUTTONUP => {
                 // 获取按下时的存储时间
                 let start_time = MOUSE_DOWN_TIME.swap(0, Ordering::SeqCst);
-                
-                if start_time > 0 {
+                let is_double_click = PENDING_DOUBLE_CLICK.swap(false, Ordering::SeqCst);
+
+                if is_double_click {
+                    // 双击选中一个单词，不管按住了多久，直接触发识别
+                    spawn_uia_worker(0, "double-click");
+                } else if start_time > 0 {
                     let now = SystemTime::now()
                         .duration_since(UNIX_EPOCH)
                         .unwrap_or_default()
@@ -52,13 +131,8 @@ unsafe extern "system" fn mouse_hook_proc(code: i32, wparam: WPARAM, lparam: LPA
                     let duration = now.saturating_sub(start_time);
 
                     // 只有当持续时间超过阈值（说明可能是拖拽选区操作）时，才触发识别
-                    if duration >= SELECTION_THRESHOLD_MS {
-                        // 【关键】不要在钩子回调里做耗时操作，开启新线程处理
-                        thread::spawn(move || {
-                            // 给 UI 一点时间完成渲染和内部状态更新
-                            thread::sleep(Duration::from_millis(50));
-                            perform_uia_detection(duration);
-                        });
+                    if duration >= SELECTION_THRESHOLD_MS.load(Ordering::Relaxed) {
+                        spawn_uia_worker(duration, "drag");
                     }
                 }
             }
@@ -70,10 +144,79 @@ unsafe extern "system" fn mouse_hook_proc(code: i32, wparam: WPARAM, lparam: LPA
     unsafe { CallNextHookEx(None, code, wparam, lparam) }
 }
 
+// -----------------------------------------------------------------------------
+// 键盘钩子回调函数：捕获 Shift+方向键/Home/End/PageUp/PageDown 以及 Ctrl+A 这类
+// 纯键盘驱动的选区操作 —— WH_MOUSE_LL 完全看不到这些
+// -----------------------------------------------------------------------------
+unsafe extern "system" fn keyboard_hook_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    // 正在合成 Ctrl+C 时跳过处理，防止我们自己的按键被当成新的一次选择操作再次触发
+    if code >= 0 && !SYNTHETIC_COPY_IN_PROGRESS.load(Ordering::SeqCst) {
+        let kb = unsafe { &*(lparam.0 as *const KBDLLHOOKSTRUCT) };
+        let vk_code = VIRTUAL_KEY(kb.vkCode as u16);
+        let msg = wparam.0 as u32;
+
+        match msg {
+            WM_KEYDOWN | WM_SYSKEYDOWN => {
+                if matches!(vk_code, VK_SHIFT | VK_LSHIFT | VK_RSHIFT) {
+                    SHIFT_DOWN.store(true, Ordering::SeqCst);
+                } else if vk_code == VK_A {
+                    // Ctrl+A：全选，不需要依赖 Shift 状态
+                    let ctrl_down = unsafe { GetAsyncKeyState(VK_CONTROL.0 as i32) } < 0;
+                    if ctrl_down {
+                        trigger_keyboard_selection();
+                    }
+                }
+            }
+            WM_KEYUP | WM_SYSKEYUP => {
+                if matches!(vk_code, VK_SHIFT | VK_LSHIFT | VK_RSHIFT) {
+                    SHIFT_DOWN.store(false, Ordering::SeqCst);
+                } else if SHIFT_DOWN.load(Ordering::SeqCst) && is_selection_extend_key(vk_code) {
+                    // Shift 仍按着，且抬起的是方向键/Home/End/PageUp/PageDown -> 选区很可能已经变化
+                    trigger_keyboard_selection();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    unsafe { CallNextHookEx(None, code, wparam, lparam) }
+}
+
+fn is_selection_extend_key(vk_code: VIRTUAL_KEY) -> bool {
+    matches!(
+        vk_code,
+        VK_LEFT | VK_RIGHT | VK_UP | VK_DOWN | VK_HOME | VK_END | VK_PRIOR | VK_NEXT
+    )
+}
+
+// 去抖后触发一次 UIA 识别，键盘场景没有“持续时间”的概念，固定传 0
+fn trigger_keyboard_selection() {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+
+    let last = LAST_KEYBOARD_TRIGGER_TIME.swap(now, Ordering::SeqCst);
+    if now.saturating_sub(last) < KEYBOARD_DEBOUNCE_MS {
+        return;
+    }
+
+    spawn_uia_worker(0, "keyboard");
+}
+
+// 两个钩子共用的“开新线程跑 UIA 识别”逻辑：钩子回调里绝不能做耗时操作
+fn spawn_uia_worker(duration_ms: u64, trigger_kind: &'static str) {
+    thread::spawn(move || {
+        // 给 UI 一点时间完成渲染和内部状态更新
+        thread::sleep(Duration::from_millis(POST_RELEASE_DELAY_MS.load(Ordering::Relaxed)));
+        perform_uia_detection(duration_ms, trigger_kind);
+    });
+}
+
 // -----------------------------------------------------------------------------
 // UIA 识别逻辑 (运行在独立线程中)
 // -----------------------------------------------------------------------------
-fn perform_uia_detection(duration_ms: u64) {
+fn perform_uia_detection(duration_ms: u64, trigger_kind: &str) {
     // 这里的 COM 初始化和 UIA 逻辑与之前完全一致
     // 注意：CoInitializeEx 必须在当前线程调用
     unsafe {
@@ -81,89 +224,435 @@ fn perform_uia_detection(duration_ms: u64) {
             return;
         }
 
-        // 尝试获取选中文本
-        match get_focused_selection() {
-            Ok(text) => {
-                if !text.trim().is_empty() {
-                    println!("--------------------------------------------------");
-                    println!("检测到长按/拖拽 ({}ms) 结束，捕获文本:", duration_ms);
-                    println!(">>> {}", text);
-                    println!("--------------------------------------------------");
-                }
-            }
-            Err(_) => {
-                // 忽略未选中或不支持的情况
-            }
+        // 尝试获取选中文本和选区的位置/来源信息，UIA 不支持 TextPattern 或者拿到空文本时退化为剪贴板方案
+        let mut selection = get_focused_selection().unwrap_or_default();
+        if selection.text.trim().is_empty() {
+            selection.text = clipboard_fallback_copy();
         }
-        
+
+        if !selection.text.trim().is_empty() {
+            emit_selection_event(selection, duration_ms, trigger_kind);
+        }
+
         // 线程结束前自动清理 COM，Rust RAII 会处理局部变量，但 CoUninitialize 需要手动吗？
         // Windows crate 的 CoInitializeEx 通常不需要显式 Uninitialize，除非极严谨的 COM 编程
         // 这里简化处理
     }
 }
 
-// 复用之前的 UIA 获取逻辑
-fn get_focused_selection() -> Result<String> {
+// 把捕获到的文本、选区矩形和来源应用信息通过 ThreadsafeFunction 送回 JS 主线程
+fn emit_selection_event(selection: FocusedSelection, duration_ms: u64, trigger_kind: &str) {
+    let guard = SELECTION_CALLBACK.lock().unwrap();
+    let Some(callback) = guard.as_ref() else {
+        return;
+    };
+
+    let event = SelectionEvent {
+        text: selection.text,
+        duration_ms: duration_ms as u32,
+        trigger_kind: trigger_kind.to_string(),
+        rect_x: selection.rect.map(|r| r.x),
+        rect_y: selection.rect.map(|r| r.y),
+        rect_width: selection.rect.map(|r| r.width),
+        rect_height: selection.rect.map(|r| r.height),
+        process_id: selection.process_id,
+        class_name: selection.class_name,
+        control_type: selection.control_type,
+    };
+
+    // 钩子回调和这里的工作线程都不在 JS 线程上，必须走 NonBlocking 模式排队
+    // 交给 Node 事件循环处理，不能直接调用 JS 函数
+    callback.call(Ok(event), ThreadsafeFunctionCallMode::NonBlocking);
+}
+
+// -----------------------------------------------------------------------------
+// 剪贴板兜底方案：当 UIA TextPattern 不可用时 (Chrome 渲染进程、Electron、
+// 很多 Qt/Win32 控件都不暴露 UIA_TextPatternId)，退化为“模拟 Ctrl+C + 读剪贴板”
+// -----------------------------------------------------------------------------
+
+// 读取剪贴板里的 CF_UNICODETEXT 文本，读不到就返回 None
+fn read_clipboard_text() -> Option<String> {
+    unsafe {
+        OpenClipboard(None).ok()?;
+
+        let result = (|| {
+            let handle = GetClipboardData(CF_UNICODETEXT.0 as u32).ok()?;
+            let ptr = GlobalLock(HGLOBAL(handle.0)) as *const u16;
+            if ptr.is_null() {
+                return None;
+            }
+
+            let mut len = 0usize;
+            while *ptr.add(len) != 0 {
+                len += 1;
+            }
+            let slice = std::slice::from_raw_parts(ptr, len);
+            let text = String::from_utf16_lossy(slice);
+            let _ = GlobalUnlock(HGLOBAL(handle.0));
+            Some(text)
+        })();
+
+        let _ = CloseClipboard();
+        result
+    }
+}
+
+// OpenClipboard 在我们自己刚打开/关闭过几次剪贴板之后，偶尔会被别的进程短暂抢占，
+// 重试几次再放弃，避免恢复用户剪贴板这种关键操作因为一次瞬时失败就直接放弃
+const CLIPBOARD_OPEN_RETRIES: u32 = 5;
+const CLIPBOARD_OPEN_RETRY_DELAY_MS: u64 = 10;
+
+fn open_clipboard_with_retry() -> bool {
+    for attempt in 0..CLIPBOARD_OPEN_RETRIES {
+        if unsafe { OpenClipboard(None) }.is_ok() {
+            return true;
+        }
+        if attempt + 1 < CLIPBOARD_OPEN_RETRIES {
+            thread::sleep(Duration::from_millis(CLIPBOARD_OPEN_RETRY_DELAY_MS));
+        }
+    }
+    false
+}
+
+// 把字符串写回剪贴板 (用于恢复用户原有的剪贴板内容，或写入 None 时仅清空)
+fn write_clipboard_text(text: Option<&str>) {
+    unsafe {
+        if !open_clipboard_with_retry() {
+            return;
+        }
+
+        let _ = EmptyClipboard();
+
+        if let Some(text) = text {
+            let mut wide: Vec<u16> = text.encode_utf16().collect();
+            wide.push(0);
+            let bytes = wide.len() * std::mem::size_of::<u16>();
+
+            if let Ok(hmem) = GlobalAlloc(GMEM_MOVEABLE, bytes) {
+                let ptr = GlobalLock(hmem) as *mut u16;
+                if ptr.is_null() {
+                    // 连锁失败，这块内存系统侧从未接手过，得自己释放
+                    let _ = GlobalFree(hmem);
+                } else {
+                    std::ptr::copy_nonoverlapping(wide.as_ptr(), ptr, wide.len());
+                    let _ = GlobalUnlock(hmem);
+                    if SetClipboardData(CF_UNICODETEXT.0 as u32, HANDLE(hmem.0)).is_err() {
+                        // SetClipboardData 失败时所有权没有转交给系统，同样需要我们自己释放
+                        let _ = GlobalFree(hmem);
+                    }
+                }
+            }
+        }
+
+        let _ = CloseClipboard();
+    }
+}
+
+// 合成一次 Ctrl+C 按键
+fn send_ctrl_c() {
+    unsafe {
+        let mut inputs = [
+            build_key_input(VK_CONTROL, false),
+            build_key_input(VK_C, false),
+            build_key_input(VK_C, true),
+            build_key_input(VK_CONTROL, true),
+        ];
+        SendInput(&mut inputs, std::mem::size_of::<INPUT>() as i32);
+    }
+}
+
+fn build_key_input(vk: VIRTUAL_KEY, key_up: bool) -> INPUT {
+    INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: vk,
+                wScan: 0,
+                dwFlags: if key_up { KEYEVENTF_KEYUP } else { KEYBD_EVENT_FLAGS(0) },
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    }
+}
+
+// 当 UIA 拿不到选区文本时调用：模拟复制，读取剪贴板，再把剪贴板还原成用户原来的内容
+fn clipboard_fallback_copy() -> String {
+    // 防止我们自己合成的 Ctrl+C 被鼠标/键盘钩子当成新的一次选择操作再次触发本函数
+    if SYNTHETIC_COPY_IN_PROGRESS.swap(true, Ordering::SeqCst) {
+        return String::new();
+    }
+
+    let original = read_clipboard_text();
+
+    send_ctrl_c();
+    thread::sleep(Duration::from_millis(CLIPBOARD_COPY_DELAY_MS));
+
+    let captured = read_clipboard_text().unwrap_or_default();
+
+    // 恢复用户原本剪贴板里的文本，避免覆盖他们复制的文字；
+    // 只保存/还原 CF_UNICODETEXT，原来剪贴板里只有图片、文件等非文本内容时，这里会把剪贴板清空
+    write_clipboard_text(original.as_deref());
+
+    SYNTHETIC_COPY_IN_PROGRESS.store(false, Ordering::SeqCst);
+
+    captured
+}
+
+// 选区在屏幕上的外接矩形 (物理像素)
+#[derive(Clone, Copy, Default)]
+struct SelectionRect {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+// get_focused_selection 的完整结果：文本 + 位置 + 来源应用信息
+#[derive(Default)]
+struct FocusedSelection {
+    text: String,
+    rect: Option<SelectionRect>,
+    process_id: u32,
+    class_name: String,
+    control_type: String,
+}
+
+// 复用之前的 UIA 获取逻辑，并补充选区矩形、所属进程/控件信息，方便上层定位悬浮工具栏
+fn get_focused_selection() -> Result<FocusedSelection> {
     unsafe {
         let uia: IUIAutomation = CoCreateInstance(&CUIAutomation, None, CLSCTX_INPROC_SERVER)?;
         let focused_element = uia.GetFocusedElement()?;
-        
+
+        // 来源应用的元数据：进程 id、类名、控件类型，和 inspect.exe 里看到的一致。
+        // 这几个 getter 都用 unwrap_or_default，单独一个元数据读取失败不该连累整次 UIA 文本读取
+        let process_id = focused_element.CurrentProcessId().unwrap_or_default() as u32;
+        let class_name = focused_element
+            .CurrentClassName()
+            .map(|s| s.to_string())
+            .unwrap_or_default();
+        let control_type = control_type_name(focused_element.CurrentControlType().unwrap_or_default());
+
         // 尝试获取 TextPattern
         let pattern_obj = focused_element.GetCurrentPattern(UIA_TextPatternId)?;
         let text_pattern: IUIAutomationTextPattern = match pattern_obj.cast() {
             Ok(p) => p,
-            Err(_) => return Ok(String::new()),
+            Err(_) => {
+                return Ok(FocusedSelection {
+                    text: String::new(),
+                    rect: None,
+                    process_id,
+                    class_name,
+                    control_type,
+                })
+            }
         };
 
         let selection_ranges = text_pattern.GetSelection()?;
         let count = selection_ranges.Length()?;
 
         if count == 0 {
-            return Ok(String::new());
+            return Ok(FocusedSelection {
+                text: String::new(),
+                rect: None,
+                process_id,
+                class_name,
+                control_type,
+            });
         }
 
         let mut full_text = String::new();
+        let mut rect = None;
         for i in 0..count {
             let range = selection_ranges.GetElement(i)?;
             let text_bstr = range.GetText(-1)?;
             full_text.push_str(&text_bstr.to_string());
+
+            // 只取第一个选区 range 的外接矩形就够用了，多行选区已经在其内部被合并过
+            if i == 0 {
+                rect = bounding_rect_of_range(&range);
+            }
         }
 
-        Ok(full_text)
+        Ok(FocusedSelection {
+            text: full_text,
+            rect,
+            process_id,
+            class_name,
+            control_type,
+        })
     }
 }
 
-#[napi]
-pub fn selection_initialize() -> Result<()> {
-  unsafe {
-        // 1. 设置全局鼠标钩子
-        let instance = GetModuleHandleW(None)?;
-        let instance_handle = HINSTANCE(instance.0);
-        let hook_id = SetWindowsHookExW(
-            WH_MOUSE_LL,
-            Some(mouse_hook_proc),
-            Some(instance_handle),
-            0,
-        )?;
+// UIA_ControlTypeId 常量只是普通的整数，这里只映射最常见的几种，其余的直接回退成十六进制 id
+fn control_type_name(control_type: UIA_CONTROLTYPE_ID) -> String {
+    match control_type {
+        UIA_EditControlTypeId => "Edit".to_string(),
+        UIA_TextControlTypeId => "Text".to_string(),
+        UIA_DocumentControlTypeId => "Document".to_string(),
+        UIA_PaneControlTypeId => "Pane".to_string(),
+        UIA_WindowControlTypeId => "Window".to_string(),
+        other => format!("0x{:X}", other.0),
+    }
+}
+
+// IUIAutomationTextRange::GetBoundingRectangles 返回一个 SAFEARRAY<double>，
+// 按每 4 个元素一组打包成 [x, y, width, height]（多行选区每行一组），这里把它们合并成一个外接矩形
+fn bounding_rect_of_range(range: &IUIAutomationTextRange) -> Option<SelectionRect> {
+    unsafe {
+        let array = range.GetBoundingRectangles().ok()?;
+        if array.is_null() {
+            return None;
+        }
 
-        if hook_id.is_invalid() {
-            eprintln!("无法安装鼠标钩子！");
-            return Ok(());
+        let mut data_ptr: *mut f64 = std::ptr::null_mut();
+        if SafeArrayAccessData(array, &mut data_ptr as *mut _ as *mut _).is_err() {
+            return None;
         }
 
+        let mut lbound = 0i32;
+        let mut ubound = 0i32;
+        let _ = SafeArrayGetLBound(array, 1, &mut lbound);
+        let _ = SafeArrayGetUBound(array, 1, &mut ubound);
+        let total = (ubound - lbound + 1).max(0) as usize;
+        let values = std::slice::from_raw_parts(data_ptr, total);
+
+        let mut union_rect: Option<SelectionRect> = None;
+        for line in values.chunks_exact(4) {
+            let (x, y, width, height) = (line[0], line[1], line[2], line[3]);
+            union_rect = Some(match union_rect {
+                None => SelectionRect { x, y, width, height },
+                Some(r) => {
+                    let left = r.x.min(x);
+                    let top = r.y.min(y);
+                    let right = (r.x + r.width).max(x + width);
+                    let bottom = (r.y + r.height).max(y + height);
+                    SelectionRect {
+                        x: left,
+                        y: top,
+                        width: right - left,
+                        height: bottom - top,
+                    }
+                }
+            });
+        }
+
+        let _ = SafeArrayUnaccessData(array);
+        let _ = SafeArrayDestroy(array);
+
+        union_rect
+    }
+}
+
+// 钩子 + 消息循环只能在安装钩子的那个线程上跑，所以这部分逻辑被挪进了专门的监听线程，
+// selection_initialize 本身立刻返回，不再占用调用者 (Node 主线程) 的线程
+//
+// `ready` 在钩子装好 (或装失败) 之后必定会被通知一次：selection_initialize 靠它等到
+// MONITOR_THREAD_ID 写入、线程消息队列建立完毕再返回，这样调用者紧接着调用的
+// selection_stop 才不会因为 MONITOR_THREAD_ID 还是 0 而把停止请求静默丢弃
+fn run_monitor_thread(ready: mpsc::Sender<()>) {
+    unsafe {
+        MONITOR_THREAD_ID.store(GetCurrentThreadId(), Ordering::SeqCst);
+
+        // 1. 设置全局鼠标钩子 + 全局键盘钩子
+        let instance = match GetModuleHandleW(None) {
+            Ok(instance) => instance,
+            Err(_) => {
+                eprintln!("无法获取模块句柄！");
+                MONITOR_THREAD_ID.store(0, Ordering::SeqCst);
+                let _ = ready.send(());
+                return;
+            }
+        };
+        let instance_handle = HINSTANCE(instance.0);
+
+        let mouse_hook_id = match SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_hook_proc), Some(instance_handle), 0) {
+            Ok(id) if !id.is_invalid() => id,
+            _ => {
+                eprintln!("无法安装鼠标钩子！");
+                MONITOR_THREAD_ID.store(0, Ordering::SeqCst);
+                let _ = ready.send(());
+                return;
+            }
+        };
+
+        let keyboard_hook_id =
+            match SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_hook_proc), Some(instance_handle), 0) {
+                Ok(id) if !id.is_invalid() => id,
+                _ => {
+                    eprintln!("无法安装键盘钩子！");
+                    let _ = UnhookWindowsHookEx(mouse_hook_id);
+                    MONITOR_THREAD_ID.store(0, Ordering::SeqCst);
+                    let _ = ready.send(());
+                    return;
+                }
+            };
+
         println!("系统监控已启动...");
-        println!("请尝试：按住鼠标左键 -> 拖拽选中文字 -> 松开鼠标");
-        println!("(短按点击不会触发)");
+        println!("请尝试：按住鼠标左键拖拽选字，或用 Shift+方向键/Home/End/Ctrl+A 选字");
+
+        // 钩子都装好了，线程的消息队列此时也已经建立，可以放行 selection_initialize 了
+        let _ = ready.send(());
 
-        // 2. 开启 Windows 消息循环 (必须，否则钩子不生效)
+        // 2. 开启 Windows 消息循环 (必须，否则钩子不生效)，直到 selection_stop 投递 WM_QUIT 才退出
         let mut msg = MSG::default();
         while GetMessageW(&mut msg, None, 0, 0).into() {
             let _ = TranslateMessage(&msg);
             DispatchMessageW(&msg);
         }
 
-        // 退出前卸载钩子
-        let _ = UnhookWindowsHookEx(hook_id);
+        // 退出前卸载两个钩子
+        let _ = UnhookWindowsHookEx(mouse_hook_id);
+        let _ = UnhookWindowsHookEx(keyboard_hook_id);
+
+        MONITOR_THREAD_ID.store(0, Ordering::SeqCst);
+    }
+}
+
+#[napi]
+pub fn selection_initialize(callback: ThreadsafeFunction<SelectionEvent>) -> Result<()> {
+    // 已经有一个监听线程在跑了，不支持重复启动，调用者必须先 selection_stop
+    if MONITOR_THREAD_ID.load(Ordering::SeqCst) != 0 {
+        eprintln!("selection_initialize 已经在运行了，请先调用 selection_stop！");
+        return Err(E_FAIL.into());
     }
+
+    // 保存 JS 回调，后续所有线程都通过它把捕获到的文本送回 Node 事件循环
+    *SELECTION_CALLBACK.lock().unwrap() = Some(callback);
+
+    // 钩子和消息循环跑在独立的 OS 线程上，这里只阻塞到钩子装好为止，不会一直占用调用线程
+    let (ready_tx, ready_rx) = mpsc::channel();
+    thread::spawn(move || run_monitor_thread(ready_tx));
+    let _ = ready_rx.recv();
+
     Ok(())
 }
+
+// 停止监听：给监听线程投递 WM_QUIT，线程收到后会自行卸载钩子并退出消息循环。
+// 之后可以重新调用 selection_initialize 再次启动监听
+#[napi]
+pub fn selection_stop() {
+    let thread_id = MONITOR_THREAD_ID.load(Ordering::SeqCst);
+    if thread_id == 0 {
+        return;
+    }
+
+    unsafe {
+        if PostThreadMessageW(thread_id, WM_QUIT, WPARAM(0), LPARAM(0)).is_err() {
+            return;
+        }
+    }
+
+    // 等监听线程卸载完钩子、把 MONITOR_THREAD_ID 清零之后再返回，
+    // 这样紧接着调用 selection_initialize 不会被当成重复启动而失败
+    while MONITOR_THREAD_ID.load(Ordering::SeqCst) == thread_id {
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+// 运行期调整拖拽阈值和松开后的延迟，不用重新编译
+#[napi]
+pub fn selection_configure(threshold_ms: u32, post_delay_ms: u32) {
+    SELECTION_THRESHOLD_MS.store(threshold_ms as u64, Ordering::SeqCst);
+    POST_RELEASE_DELAY_MS.store(post_delay_ms as u64, Ordering::SeqCst);
+}